@@ -6,6 +6,40 @@ use std::error;
 use std::process;
 
 fn main() -> Result<(), Box<dyn error::Error>> {
+    // The `branch` subcommand is a separate mode of operation from the hook
+    // path: it creates a correctly-named branch for an issue id.
+    if env::args().nth(1).as_deref() == Some("branch") {
+        return run_branch_subcommand();
+    }
+
+    run_hook();
+    Ok(())
+}
+
+/// Create or switch to a branch for the issue id given as the second argument.
+fn run_branch_subcommand() -> Result<(), Box<dyn error::Error>> {
+    let id = match env::args().nth(2) {
+        Some(id) => id,
+        None => {
+            eprintln!("Usage: prepare-commit-msg branch <issue-id>");
+            process::exit(64);
+        }
+    };
+
+    match create_or_switch_branch(&id) {
+        Ok(branch) => {
+            println!("Switched to branch '{}'", branch);
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("Failed to create branch for {}: {}", id, e);
+            process::exit(1);
+        }
+    }
+}
+
+/// Run the `prepare-commit-msg` hook path.
+fn run_hook() {
     let commit_filename = env::args().nth(1);
     let commit_source = env::args().nth(2);
     let current_branch = get_current_branch();
@@ -46,6 +80,4 @@ fn main() -> Result<(), Box<dyn error::Error>> {
             process::exit(2);
         }
     }
-
-    Ok(())
 }
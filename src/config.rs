@@ -0,0 +1,205 @@
+//! Configuration loaded from a `.task_hook.toml` file at the repository root.
+//!
+//! Modeled on git-next's `RepoConfig`: a small serde-deserialized struct with
+//! sensible defaults so the hook keeps working with zero configuration, plus a
+//! `parse`/`load` pair that surfaces TOML and regex errors as `Result`s instead
+//! of panicking the way the old `get_work_item_regex` did.
+
+use crate::forge::ForgeConfig;
+use regex::Regex;
+use serde::Deserialize;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+/// Default regex pattern for task/pbi/bug/feature/feat branches.
+pub const DEFAULT_BRANCH_PATTERN: &str = r#"^(?:task|pbi|bug|feature|feat)/([0-9]+).*$"#;
+/// Default output template; `{id}` is replaced with the extracted work-item id.
+pub const DEFAULT_TEMPLATE: &str = "#{id}";
+/// Default template for branch names created by the `branch` subcommand.
+pub const DEFAULT_BRANCH_TEMPLATE: &str = "task/{id}-{slug}";
+/// Default base branch new branches are created from.
+pub const DEFAULT_BASE_BRANCH: &str = "main";
+/// Name of the configuration file discovered at the repository root.
+pub const CONFIG_FILE_NAME: &str = ".task_hook.toml";
+
+/// Where the rendered token is placed relative to the commit message body.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Position {
+    /// Append the token after the message (the historical behavior).
+    #[default]
+    Append,
+    /// Prepend the token before the message.
+    Prepend,
+}
+
+/// Branch-matching and message-formatting rules for the hook.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Regex matched against the branch name; one capture group holds the id.
+    pub branch_pattern: String,
+    /// 1-based index of the capture group that holds the work-item id.
+    pub id_group: usize,
+    /// Template for the inserted token; `{id}` is substituted with the id.
+    pub template: String,
+    /// Whether the token is appended to or prepended to the message.
+    pub position: Position,
+    /// Template for branch names created by the `branch` subcommand; `{id}` and
+    /// `{slug}` are substituted.
+    pub branch_template: String,
+    /// Base branch new branches are created from.
+    pub base_branch: String,
+    /// Optional forge to look up issue titles against for online enrichment.
+    pub forge: Option<ForgeConfig>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            branch_pattern: DEFAULT_BRANCH_PATTERN.to_string(),
+            id_group: 1,
+            template: DEFAULT_TEMPLATE.to_string(),
+            position: Position::Append,
+            branch_template: DEFAULT_BRANCH_TEMPLATE.to_string(),
+            base_branch: DEFAULT_BASE_BRANCH.to_string(),
+            forge: None,
+        }
+    }
+}
+
+impl Config {
+    /// Parse a configuration from TOML source, validating the branch pattern so
+    /// a broken regex is reported at load time rather than on first use.
+    pub fn parse(source: &str) -> Result<Config, Box<dyn Error>> {
+        let config: Config = toml::from_str(source)?;
+        config.compile_regex()?;
+        Ok(config)
+    }
+
+    /// Load configuration from `<root>/.task_hook.toml`, falling back to the
+    /// built-in defaults when the file does not exist.
+    pub fn load(root: &Path) -> Result<Config, Box<dyn Error>> {
+        let path = root.join(CONFIG_FILE_NAME);
+        match fs::read_to_string(&path) {
+            Ok(source) => Config::parse(&source),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(Box::new(e)),
+        }
+    }
+
+    /// Discover the repository root and load the configuration from it. Any
+    /// failure to locate the root falls back to the defaults so the hook never
+    /// blocks a commit on configuration discovery.
+    pub fn discover() -> Result<Config, Box<dyn Error>> {
+        let repo = match gix::discover(".") {
+            Ok(repo) => repo,
+            Err(_) => return Ok(Config::default()),
+        };
+        match repo.workdir() {
+            Some(root) => Config::load(root),
+            None => Ok(Config::default()),
+        }
+    }
+
+    /// Compile the configured branch pattern.
+    pub fn compile_regex(&self) -> Result<Regex, Box<dyn Error>> {
+        Ok(Regex::new(&self.branch_pattern)?)
+    }
+
+    /// Extract the work-item id from a branch name, if it matches the pattern.
+    ///
+    /// Compiles the pattern on each call; callers that match many branches in a
+    /// loop should compile once with [`compile_regex`](Self::compile_regex) and
+    /// use [`work_item_id_with`](Self::work_item_id_with) instead.
+    pub fn work_item_id(&self, branch_name: &str) -> Option<String> {
+        let regex = self.compile_regex().ok()?;
+        self.work_item_id_with(&regex, branch_name)
+    }
+
+    /// Extract the work-item id using a pre-compiled pattern, avoiding the
+    /// per-call `Regex::new` when matching against a list of branches.
+    pub fn work_item_id_with(&self, regex: &Regex, branch_name: &str) -> Option<String> {
+        let captures = regex.captures(branch_name)?;
+        captures.get(self.id_group).map(|m| m.as_str().to_string())
+    }
+
+    /// Render the token for a given id using the configured template.
+    pub fn render(&self, id: &str) -> String {
+        self.template.replace("{id}", id)
+    }
+
+    /// Build a branch name for an id, substituting an optional slug. When no
+    /// slug is available the `{slug}` placeholder and any adjacent separator are
+    /// dropped, yielding e.g. `task/123` rather than `task/123-`.
+    pub fn branch_name(&self, id: &str, slug: Option<&str>) -> String {
+        let name = self.branch_template.replace("{id}", id);
+        match slug {
+            Some(slug) if !slug.is_empty() => name.replace("{slug}", slug),
+            _ => name
+                .replace("-{slug}", "")
+                .replace("_{slug}", "")
+                .replace("{slug}", ""),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_config_matches_legacy_behavior() {
+        let config = Config::default();
+        let id = config.work_item_id("task/123-some-feature").unwrap();
+        assert_eq!(id, "123");
+        assert_eq!(config.render(&id), "#123");
+        assert_eq!(config.position, Position::Append);
+    }
+
+    #[test]
+    fn test_parse_custom_template_and_position() {
+        let config = Config::parse(
+            r#"
+            branch_pattern = "^(?:JIRA)-([0-9]+)$"
+            template = "[{id}] "
+            position = "prepend"
+            "#,
+        )
+        .unwrap();
+        let id = config.work_item_id("JIRA-42").unwrap();
+        assert_eq!(id, "42");
+        assert_eq!(config.render(&id), "[42] ");
+        assert_eq!(config.position, Position::Prepend);
+    }
+
+    #[test]
+    fn test_parse_capture_group_index() {
+        let config = Config::parse(
+            r#"
+            branch_pattern = "^(GH)-([0-9]+)$"
+            id_group = 2
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.work_item_id("GH-7").as_deref(), Some("7"));
+    }
+
+    #[test]
+    fn test_branch_name_with_and_without_slug() {
+        let config = Config::default();
+        assert_eq!(
+            config.branch_name("123", Some("null-pointer")),
+            "task/123-null-pointer"
+        );
+        assert_eq!(config.branch_name("123", None), "task/123");
+        assert_eq!(config.branch_name("123", Some("")), "task/123");
+    }
+
+    #[test]
+    fn test_parse_invalid_regex_is_error() {
+        let result = Config::parse(r#"branch_pattern = "([0-9]+""#);
+        assert!(result.is_err(), "invalid regex should surface as an error");
+    }
+}
@@ -0,0 +1,170 @@
+//! Minimal best-effort forge client used to enrich a commit message with the
+//! issue title behind an extracted work-item id.
+//!
+//! The network path is deliberately forgiving: every lookup returns `None` on
+//! any error (offline, 404, auth failure) so a commit is never blocked, and
+//! results are cached under the git dir to avoid repeated calls on `--amend`.
+
+use serde::Deserialize;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// API dialect spoken by the configured forge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ForgeKind {
+    Github,
+    Gitea,
+    Forgejo,
+}
+
+fn default_host() -> String {
+    "api.github.com".to_string()
+}
+
+fn default_token_env() -> String {
+    "TASK_HOOK_TOKEN".to_string()
+}
+
+/// Where and how to reach the forge hosting the issues.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ForgeConfig {
+    /// Which forge API to speak.
+    pub kind: ForgeKind,
+    /// API host, e.g. `api.github.com` or `git.example.org`.
+    #[serde(default = "default_host")]
+    pub host: String,
+    /// Repository owner (user or organisation).
+    pub owner: String,
+    /// Repository name.
+    pub repo: String,
+    /// Name of the environment variable carrying the auth token.
+    #[serde(default = "default_token_env")]
+    pub token_env: String,
+}
+
+#[derive(Deserialize)]
+struct Issue {
+    title: String,
+}
+
+impl ForgeConfig {
+    /// Build the issues endpoint URL for an id, honoring the forge dialect.
+    fn issue_url(&self, id: &str) -> String {
+        match self.kind {
+            ForgeKind::Github => format!(
+                "https://{}/repos/{}/{}/issues/{}",
+                self.host, self.owner, self.repo, id
+            ),
+            ForgeKind::Gitea | ForgeKind::Forgejo => format!(
+                "https://{}/api/v1/repos/{}/{}/issues/{}",
+                self.host, self.owner, self.repo, id
+            ),
+        }
+    }
+
+    /// Fetch the issue title with a single short-timeout GET, returning `None`
+    /// on any error so the caller can fall back to the plain token.
+    pub fn fetch_title(&self, id: &str) -> Option<String> {
+        let url = self.issue_url(id);
+        let mut request = ureq::get(&url).timeout(Duration::from_secs(3));
+        if let Ok(token) = std::env::var(&self.token_env) {
+            if !token.is_empty() {
+                let header = match self.kind {
+                    ForgeKind::Github => format!("Bearer {}", token),
+                    ForgeKind::Gitea | ForgeKind::Forgejo => format!("token {}", token),
+                };
+                request = request.set("Authorization", &header);
+            }
+        }
+        let issue: Issue = request.call().ok()?.into_json().ok()?;
+        Some(issue.title)
+    }
+}
+
+/// Path of the cached title for an id, under the git dir.
+fn cache_path(git_dir: &Path, id: &str) -> PathBuf {
+    git_dir.join("task_hook").join("titles").join(id)
+}
+
+/// Read a previously cached title for an id, if present.
+pub fn cached_title(git_dir: &Path, id: &str) -> Option<String> {
+    fs::read_to_string(cache_path(git_dir, id))
+        .ok()
+        .map(|s| s.trim_end_matches('\n').to_string())
+}
+
+/// Persist a fetched title for an id so `--amend` does not re-hit the network.
+pub fn cache_title(git_dir: &Path, id: &str, title: &str) -> Result<(), Box<dyn Error>> {
+    let path = cache_path(git_dir, id);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, title)?;
+    Ok(())
+}
+
+/// Resolve the issue title for an id, preferring the cache and falling back to
+/// a best-effort network fetch whose result is then cached. Returns `None`
+/// when the title cannot be determined.
+pub fn resolve_title(forge: &ForgeConfig, git_dir: Option<&Path>, id: &str) -> Option<String> {
+    if let Some(dir) = git_dir {
+        if let Some(title) = cached_title(dir, id) {
+            return Some(title);
+        }
+    }
+    let title = forge.fetch_title(id)?;
+    if let Some(dir) = git_dir {
+        let _ = cache_title(dir, id, &title);
+    }
+    Some(title)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_github_issue_url() {
+        let forge = ForgeConfig {
+            kind: ForgeKind::Github,
+            host: "api.github.com".to_string(),
+            owner: "arejensen".to_string(),
+            repo: "task_hook".to_string(),
+            token_env: "TASK_HOOK_TOKEN".to_string(),
+        };
+        assert_eq!(
+            forge.issue_url("123"),
+            "https://api.github.com/repos/arejensen/task_hook/issues/123"
+        );
+    }
+
+    #[test]
+    fn test_gitea_issue_url() {
+        let forge = ForgeConfig {
+            kind: ForgeKind::Gitea,
+            host: "git.example.org".to_string(),
+            owner: "team".to_string(),
+            repo: "proj".to_string(),
+            token_env: "TASK_HOOK_TOKEN".to_string(),
+        };
+        assert_eq!(
+            forge.issue_url("7"),
+            "https://git.example.org/api/v1/repos/team/proj/issues/7"
+        );
+    }
+
+    #[test]
+    fn test_cache_round_trip() -> Result<(), Box<dyn Error>> {
+        let dir = tempfile::tempdir()?;
+        assert_eq!(cached_title(dir.path(), "42"), None);
+        cache_title(dir.path(), "42", "Null pointer in tokenizer")?;
+        assert_eq!(
+            cached_title(dir.path(), "42").as_deref(),
+            Some("Null pointer in tokenizer")
+        );
+        Ok(())
+    }
+}